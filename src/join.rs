@@ -0,0 +1,297 @@
+//! A heterogeneous, allocation-free counterpart to [`crate::ShutdownFuture`],
+//! built via the [`shutdown!`] macro in the style of `core::future::join!`.
+//!
+//! Where `ShutdownFuture` boxes every trigger and task into
+//! `Pin<Box<dyn Future<...>>>` behind a single uniform output type,
+//! `ShutdownJoin` stores a fixed tuple of triggers and a fixed tuple of
+//! tasks inline, each keeping its own concrete `Output` type, and polls them
+//! in place.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// The maximum number of triggers, or tasks, that `shutdown!` can combine
+/// without heap allocation. Bumping this means adding another
+/// `impl_poll_tuple!` invocation below.
+const MAX_ARITY: usize = 6;
+
+/// A single trigger or task slot: starts out polling `Fut`, then holds its
+/// output once `Fut` completes, until it is collected.
+pub enum Slot<Fut: Future> {
+    Polling(Fut),
+    Done(Fut::Output),
+    Taken,
+}
+
+impl<Fut: Future> Slot<Fut> {
+    /// Wraps a future as a pending slot. Used by the [`shutdown!`] macro.
+    pub fn polling(fut: Fut) -> Self {
+        Slot::Polling(fut)
+    }
+
+    /// Polls the slot if it's still pending. Returns `true` if this call
+    /// just completed it.
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool {
+        // SAFETY: `self` is only ever reached through a pinned reference to
+        // the `ShutdownJoin` that owns it, and `fut` is never moved out of
+        // its slot while pending.
+        let this = unsafe { self.get_unchecked_mut() };
+        match this {
+            Slot::Polling(fut) => {
+                let fut = unsafe { Pin::new_unchecked(fut) };
+                match fut.poll(cx) {
+                    Poll::Ready(value) => {
+                        *this = Slot::Done(value);
+                        true
+                    }
+                    Poll::Pending => false,
+                }
+            }
+            Slot::Done(_) | Slot::Taken => false,
+        }
+    }
+
+    /// Takes the slot's output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slot has not completed.
+    fn take(&mut self) -> Fut::Output {
+        match std::mem::replace(self, Slot::Taken) {
+            Slot::Done(value) => value,
+            _ => unreachable!("slot polled to completion before take"),
+        }
+    }
+}
+
+/// Polls every not-yet-finished slot of a fixed-size tuple of [`Slot`]s in
+/// place. Implemented below for tuples up to [`MAX_ARITY`] elements.
+pub trait PollTuple {
+    type Output;
+
+    const LEN: usize;
+
+    /// Polls each slot not already marked done in `done`, flips `done[i]`
+    /// for slots that complete this call, and returns the index of the
+    /// first slot that completed (if any).
+    fn poll_tuple(self: Pin<&mut Self>, cx: &mut Context<'_>, done: &mut [bool]) -> Option<usize>;
+
+    /// Collects every slot's output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any slot has not completed.
+    fn take_tuple(&mut self) -> Self::Output;
+}
+
+macro_rules! impl_poll_tuple {
+    ($len:expr; $($T:ident : $idx:tt),*) => {
+        impl<$($T: Future),*> PollTuple for ($(Slot<$T>,)*) {
+            type Output = ($($T::Output,)*);
+
+            const LEN: usize = $len;
+
+            fn poll_tuple(
+                self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                _done: &mut [bool],
+            ) -> Option<usize> {
+                // SAFETY: structural pinning into tuple fields; no field is
+                // moved while pinned.
+                let _this = unsafe { self.get_unchecked_mut() };
+                #[allow(unused_mut)]
+                let mut first = None;
+                $(
+                    if !_done[$idx] {
+                        let slot = unsafe { Pin::new_unchecked(&mut _this.$idx) };
+                        if slot.poll(_cx) {
+                            _done[$idx] = true;
+                            if first.is_none() {
+                                first = Some($idx);
+                            }
+                        }
+                    }
+                )*
+                first
+            }
+
+            #[allow(clippy::unused_unit)]
+            fn take_tuple(&mut self) -> Self::Output {
+                ($(self.$idx.take(),)*)
+            }
+        }
+    };
+}
+
+impl_poll_tuple!(0;);
+impl_poll_tuple!(1; T0:0);
+impl_poll_tuple!(2; T0:0, T1:1);
+impl_poll_tuple!(3; T0:0, T1:1, T2:2);
+impl_poll_tuple!(4; T0:0, T1:1, T2:2, T3:3);
+impl_poll_tuple!(5; T0:0, T1:1, T2:2, T3:3, T4:4);
+impl_poll_tuple!(6; T0:0, T1:1, T2:2, T3:3, T4:4, T5:5);
+
+enum JoinState {
+    WaitingForTrigger,
+    RunningAction,
+    JoiningTasks,
+}
+
+/// What caused a [`ShutdownJoin`] to begin shutting down: the index of the
+/// trigger or task that completed first, in the order passed to
+/// [`shutdown!`].
+pub enum ShutdownJoinCause {
+    Trigger(usize),
+    Task(usize),
+}
+
+/// The result of a [`ShutdownJoin`]: why it shut down, and every task's
+/// output as a tuple, in the order the tasks were passed to [`shutdown!`].
+pub struct ShutdownJoinOutput<Tasks> {
+    pub cause: ShutdownJoinCause,
+    pub tasks: Tasks,
+}
+
+/// A fixed set of triggers, tasks, and a cleanup future, driven without
+/// boxing. Build one with the [`shutdown!`] macro rather than calling
+/// [`ShutdownJoin::new`] directly.
+pub struct ShutdownJoin<Triggers, Tasks, C>
+where
+    Triggers: PollTuple,
+    Tasks: PollTuple,
+    C: Future<Output = ()>,
+{
+    triggers: Triggers,
+    tasks: Tasks,
+    cleanup: C,
+    trigger_done: [bool; MAX_ARITY],
+    task_done: [bool; MAX_ARITY],
+    state: JoinState,
+    cause: Option<ShutdownJoinCause>,
+}
+
+impl<Triggers, Tasks, C> ShutdownJoin<Triggers, Tasks, C>
+where
+    Triggers: PollTuple,
+    Tasks: PollTuple,
+    C: Future<Output = ()>,
+{
+    pub fn new(triggers: Triggers, tasks: Tasks, cleanup: C) -> Self {
+        Self {
+            triggers,
+            tasks,
+            cleanup,
+            trigger_done: [false; MAX_ARITY],
+            task_done: [false; MAX_ARITY],
+            state: JoinState::WaitingForTrigger,
+            cause: None,
+        }
+    }
+}
+
+impl<Triggers, Tasks, C> Future for ShutdownJoin<Triggers, Tasks, C>
+where
+    Triggers: PollTuple,
+    Tasks: PollTuple,
+    C: Future<Output = ()>,
+{
+    type Output = ShutdownJoinOutput<Tasks::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: none of the projected fields (`triggers`, `tasks`,
+        // `cleanup`) are moved out of `self` while pinned; `self` is only
+        // ever accessed through `Pin`.
+        let this = unsafe { self.get_unchecked_mut() };
+
+        match this.state {
+            JoinState::WaitingForTrigger => {
+                let triggers = unsafe { Pin::new_unchecked(&mut this.triggers) };
+                if let Some(index) = triggers.poll_tuple(cx, &mut this.trigger_done) {
+                    this.cause = Some(ShutdownJoinCause::Trigger(index));
+                    this.state = JoinState::RunningAction;
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                let tasks = unsafe { Pin::new_unchecked(&mut this.tasks) };
+                if let Some(index) = tasks.poll_tuple(cx, &mut this.task_done) {
+                    this.cause = Some(ShutdownJoinCause::Task(index));
+                    this.state = JoinState::RunningAction;
+                    cx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            }
+            JoinState::RunningAction => {
+                let cleanup = unsafe { Pin::new_unchecked(&mut this.cleanup) };
+                if cleanup.poll(cx).is_ready() {
+                    this.state = JoinState::JoiningTasks;
+                    cx.waker().wake_by_ref();
+                }
+                Poll::Pending
+            }
+            JoinState::JoiningTasks => {
+                let tasks = unsafe { Pin::new_unchecked(&mut this.tasks) };
+                tasks.poll_tuple(cx, &mut this.task_done);
+                if this.task_done[..Tasks::LEN].iter().all(|&d| d) {
+                    Poll::Ready(ShutdownJoinOutput {
+                        cause: this.cause.take().expect("cause set before JoiningTasks"),
+                        tasks: this.tasks.take_tuple(),
+                    })
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+    }
+}
+
+/// Builds a [`ShutdownJoin`] from a fixed set of triggers, tasks, and a
+/// cleanup future, in the style of `core::future::join!`. Unlike
+/// [`crate::ShutdownFuture::new`], nothing here is boxed, so each trigger
+/// and task keeps its own concrete `Output` type.
+///
+/// ```ignore
+/// let shutdown = shutdown! {
+///     triggers: [sigint(), sigterm()],
+///     tasks: [worker_one(), worker_two()],
+///     cleanup: flush_buffers(),
+/// };
+/// let result = shutdown.await;
+/// ```
+///
+/// Supports up to [`MAX_ARITY`] triggers and up to `MAX_ARITY` tasks.
+#[macro_export]
+macro_rules! shutdown {
+    (
+        triggers: [ $($trigger:expr),* $(,)? ],
+        tasks: [ $($task:expr),* $(,)? ],
+        cleanup: $cleanup:expr $(,)?
+    ) => {
+        $crate::ShutdownJoin::new(
+            ($($crate::Slot::polling($trigger),)*),
+            ($($crate::Slot::polling($task),)*),
+            $cleanup,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Immediate;
+
+    #[test]
+    fn shutdown_macro_joins_tasks_with_cause() {
+        let join = crate::shutdown! {
+            triggers: [Immediate(Some("sig"))],
+            tasks: [Immediate(Some(1)), Immediate(Some(2))],
+            cleanup: Immediate(Some(())),
+        };
+
+        let output = pollster::block_on(join);
+        assert!(matches!(output.cause, ShutdownJoinCause::Trigger(0)));
+        assert_eq!(output.tasks, (1, 2));
+    }
+}
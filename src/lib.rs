@@ -1,40 +1,270 @@
 use std::{
+    collections::VecDeque,
     future::Future,
     pin::Pin,
-    task::{Context, Poll},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
 };
 
+use futures_core::future::FusedFuture;
+
+mod join;
+#[cfg(test)]
+mod test_support;
+
+pub use join::{PollTuple, ShutdownJoin, ShutdownJoinCause, ShutdownJoinOutput, Slot};
+
 enum ShutdownState {
     WaitingForTrigger,
     RunningAction,
     JoiningTasks,
+    Finished,
+}
+
+/// Identifies which child future a wakeup came from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ChildId {
+    Trigger(usize),
+    Task(usize),
+}
+
+/// State shared between `ShutdownFuture` and the per-child wakers it hands
+/// out: which children have been woken since the last poll, and the parent
+/// waker to notify when that happens.
+struct Shared {
+    parent: Option<Waker>,
+    ready: VecDeque<ChildId>,
+}
+
+/// A `Wake` implementation for a single trigger or task. Waking it records
+/// its id as ready and forwards the wakeup to the parent future, so only
+/// the children that actually made progress get polled again.
+struct ChildWaker {
+    id: ChildId,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Wake for ChildWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        // Take the parent waker out and drop the lock before calling it:
+        // `waker.wake()` runs arbitrary executor code, which may re-enter
+        // `ShutdownFuture::poll` synchronously and deadlock on this same
+        // (non-reentrant) mutex.
+        let waker = {
+            let mut shared = self.shared.lock().unwrap();
+            shared.ready.push_back(self.id);
+            shared.parent.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// A task slot: either still running, or finished and holding its output
+/// until the whole `ShutdownFuture` resolves.
+enum TaskSlot<TaskReturn> {
+    Pending(Pin<Box<dyn Future<Output = TaskReturn>>>),
+    Done(TaskReturn),
+}
+
+/// What caused a `ShutdownFuture` to begin shutting down.
+pub enum ShutdownCause<TriggerReturn> {
+    /// The trigger at `index` completed with `value`.
+    Trigger { index: usize, value: TriggerReturn },
+    /// The task at `index` completed; its output is in the `tasks` vec of
+    /// the corresponding [`ShutdownOutput`].
+    Task { index: usize },
+}
+
+/// A state transition of a [`ShutdownFuture`], passed to the callback
+/// registered with [`ShutdownFuture::on_transition`].
+pub enum ShutdownEvent {
+    /// `WaitingForTrigger -> RunningAction`: the trigger at `index` fired.
+    TriggerFired { index: usize },
+    /// `WaitingForTrigger -> RunningAction`: the task at `index` completed
+    /// before any trigger did.
+    TaskFiredFirst { index: usize },
+    /// `RunningAction -> JoiningTasks`: clean-up finished.
+    CleanupFinished,
+    /// A task finished while its output was being joined.
+    TaskJoined { index: usize },
+    /// The future resolved.
+    Finished,
+}
+
+/// The result of a [`ShutdownFuture`]: why it shut down, and the output of
+/// every task, in the order the tasks were supplied.
+///
+/// If the deadline passed to [`ShutdownFuture::new`] fired before every
+/// task finished, `timed_out` is `true` and any task still running at that
+/// point is dropped rather than joined, leaving its slot `None`.
+pub struct ShutdownOutput<TriggerReturn, TaskReturn> {
+    pub cause: ShutdownCause<TriggerReturn>,
+    pub tasks: Vec<Option<TaskReturn>>,
+    pub timed_out: bool,
 }
 
 /// A concurrent future for awaiting multiple triggers,
 /// running clean-up, and joining tasks.
-/// 
+///
 /// If any of the triggers or tasks complete, the clean-up
 /// future is awaited and then all remaining tasks are awaited.
 pub struct ShutdownFuture<TriggerReturn, TaskReturn, F: Future<Output = ()>> {
-    triggers: Vec<Pin<Box<dyn Future<Output = TriggerReturn>>>>,
-    tasks: Vec<Pin<Box<dyn Future<Output = TaskReturn>>>>,
+    triggers: Vec<Option<Pin<Box<dyn Future<Output = TriggerReturn>>>>>,
+    tasks: Vec<TaskSlot<TaskReturn>>,
+    trigger_wakers: Vec<Waker>,
+    task_wakers: Vec<Waker>,
     cleanup: Pin<Box<F>>,
+    deadline: Option<Pin<Box<dyn Future<Output = ()>>>>,
+    shared: Arc<Mutex<Shared>>,
     state: ShutdownState,
+    first_poll: bool,
+    cause: Option<ShutdownCause<TriggerReturn>>,
+    on_transition: Option<Box<dyn FnMut(ShutdownEvent)>>,
+}
+
+// SAFETY: every future `ShutdownFuture` owns is already pinned behind its
+// own `Pin<Box<_>>` (`triggers`, `tasks`, `cleanup`, `deadline`); moving
+// `ShutdownFuture` itself only moves those pointers, never the pinned data
+// they point to. `TriggerReturn`/`TaskReturn` are stored by value but are
+// plain output data, not futures, so moving them is always sound.
+impl<TriggerReturn, TaskReturn, F: Future<Output = ()>> Unpin
+    for ShutdownFuture<TriggerReturn, TaskReturn, F>
+{
 }
 
 impl<TriggerReturn, TaskReturn, F: Future<Output = ()>>
     ShutdownFuture<TriggerReturn, TaskReturn, F>
 {
+    /// `deadline`, if given, bounds how long clean-up and task-joining may
+    /// take: once it fires, clean-up is no longer polled, any tasks still
+    /// running are dropped, and the future resolves immediately with
+    /// [`ShutdownOutput::timed_out`] set and a `None` slot in
+    /// [`ShutdownOutput::tasks`] for each task that did not complete.
     pub fn new(
         triggers: Vec<Pin<Box<dyn Future<Output = TriggerReturn>>>>,
         tasks: Vec<Pin<Box<dyn Future<Output = TaskReturn>>>>,
         cleanup: F,
+        deadline: Option<Pin<Box<dyn Future<Output = ()>>>>,
     ) -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            parent: None,
+            ready: VecDeque::new(),
+        }));
+
+        let trigger_wakers = (0..triggers.len())
+            .map(|i| {
+                Waker::from(Arc::new(ChildWaker {
+                    id: ChildId::Trigger(i),
+                    shared: shared.clone(),
+                }))
+            })
+            .collect();
+        let task_wakers = (0..tasks.len())
+            .map(|i| {
+                Waker::from(Arc::new(ChildWaker {
+                    id: ChildId::Task(i),
+                    shared: shared.clone(),
+                }))
+            })
+            .collect();
+
         Self {
-            triggers,
-            tasks,
+            triggers: triggers.into_iter().map(Some).collect(),
+            tasks: tasks.into_iter().map(TaskSlot::Pending).collect(),
+            trigger_wakers,
+            task_wakers,
             cleanup: Box::pin(cleanup),
+            deadline,
+            shared,
             state: ShutdownState::WaitingForTrigger,
+            first_poll: true,
+            cause: None,
+            on_transition: None,
+        }
+    }
+
+    /// Registers a callback invoked on every [`ShutdownEvent`].
+    pub fn on_transition(mut self, callback: impl FnMut(ShutdownEvent) + 'static) -> Self {
+        self.on_transition = Some(Box::new(callback));
+        self
+    }
+
+    /// Invokes the registered `on_transition` callback, if any.
+    fn emit(&mut self, event: ShutdownEvent) {
+        if let Some(callback) = &mut self.on_transition {
+            callback(event);
+        }
+    }
+
+    /// Builds the final output once every task has completed normally.
+    fn finish_completed(&mut self) -> ShutdownOutput<TriggerReturn, TaskReturn> {
+        let tasks = self
+            .tasks
+            .drain(..)
+            .map(|slot| match slot {
+                TaskSlot::Done(value) => Some(value),
+                TaskSlot::Pending(_) => unreachable!("all tasks are done"),
+            })
+            .collect();
+        ShutdownOutput {
+            cause: self.cause.take().expect("cause set before RunningAction"),
+            tasks,
+            timed_out: false,
+        }
+    }
+
+    /// Builds the final output when the deadline fires first, dropping any
+    /// task that hadn't completed yet. Clean-up is left unpolled.
+    fn finish_timed_out(&mut self) -> ShutdownOutput<TriggerReturn, TaskReturn> {
+        let tasks = self
+            .tasks
+            .drain(..)
+            .map(|slot| match slot {
+                TaskSlot::Done(value) => Some(value),
+                TaskSlot::Pending(_) => None,
+            })
+            .collect();
+        ShutdownOutput {
+            cause: self.cause.take().expect("cause set before RunningAction"),
+            tasks,
+            timed_out: true,
+        }
+    }
+
+    /// Polls a single trigger by index using its own waker, tombstoning the
+    /// slot and returning its output if it completes.
+    fn poll_trigger(&mut self, i: usize) -> Option<TriggerReturn> {
+        let Some(trigger) = &mut self.triggers[i] else {
+            return None;
+        };
+        let mut cx = Context::from_waker(&self.trigger_wakers[i]);
+        match trigger.as_mut().poll(&mut cx) {
+            Poll::Ready(value) => {
+                self.triggers[i] = None;
+                Some(value)
+            }
+            Poll::Pending => None,
+        }
+    }
+
+    /// Polls a single task by index using its own waker. If it completes,
+    /// its output is stashed in the slot and `true` is returned.
+    fn poll_task(&mut self, i: usize) -> bool {
+        let TaskSlot::Pending(task) = &mut self.tasks[i] else {
+            return false;
+        };
+        let mut cx = Context::from_waker(&self.task_wakers[i]);
+        if let Poll::Ready(value) = task.as_mut().poll(&mut cx) {
+            self.tasks[i] = TaskSlot::Done(value);
+            true
+        } else {
+            false
         }
     }
 }
@@ -42,48 +272,288 @@ impl<TriggerReturn, TaskReturn, F: Future<Output = ()>>
 impl<TriggerReturn, TaskReturn, F: Future<Output = ()>> Future
     for ShutdownFuture<TriggerReturn, TaskReturn, F>
 {
-    type Output = ();
+    type Output = ShutdownOutput<TriggerReturn, TaskReturn>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.shared.lock().unwrap().parent = Some(cx.waker().clone());
+
+        if self.first_poll {
+            self.first_poll = false;
+            let mut shared = self.shared.lock().unwrap();
+            shared
+                .ready
+                .extend((0..self.triggers.len()).map(ChildId::Trigger));
+            shared
+                .ready
+                .extend((0..self.tasks.len()).map(ChildId::Task));
+        }
+
         match self.state {
             ShutdownState::WaitingForTrigger => {
-                for trigger in self.triggers.iter_mut() {
-                    if trigger.as_mut().poll(cx).is_ready() {
-                        cx.waker().wake_by_ref();
-                        self.state = ShutdownState::RunningAction;
-                        break;
-                    }
-                }
-                for (i, task) in self.tasks.iter_mut().enumerate() {
-                    if task.as_mut().poll(cx).is_ready() {
-                        #[allow(unused_must_use)]
-                        {
-                            self.tasks.remove(i);
+                let mut ready: VecDeque<ChildId> =
+                    std::mem::take(&mut self.shared.lock().unwrap().ready);
+                while let Some(id) = ready.pop_front() {
+                    match id {
+                        ChildId::Trigger(i) => {
+                            if let Some(value) = self.poll_trigger(i) {
+                                self.cause = Some(ShutdownCause::Trigger { index: i, value });
+                                self.state = ShutdownState::RunningAction;
+                                self.emit(ShutdownEvent::TriggerFired { index: i });
+                                cx.waker().wake_by_ref();
+                                break;
+                            }
+                        }
+                        ChildId::Task(i) => {
+                            if self.poll_task(i) {
+                                self.cause = Some(ShutdownCause::Task { index: i });
+                                self.state = ShutdownState::RunningAction;
+                                self.emit(ShutdownEvent::TaskFiredFirst { index: i });
+                                cx.waker().wake_by_ref();
+                                break;
+                            }
                         }
-                        cx.waker().wake_by_ref();
-                        self.state = ShutdownState::RunningAction;
-                        break;
                     }
                 }
+                // Entries left in `ready` were dequeued but never polled
+                // (we stopped early once a transition fired); put them back
+                // so they aren't lost.
+                if !ready.is_empty() {
+                    self.shared.lock().unwrap().ready.extend(ready);
+                }
                 Poll::Pending
             }
             ShutdownState::RunningAction => {
+                if let Some(deadline) = &mut self.deadline {
+                    if deadline.as_mut().poll(cx).is_ready() {
+                        self.state = ShutdownState::Finished;
+                        self.emit(ShutdownEvent::Finished);
+                        return Poll::Ready(self.finish_timed_out());
+                    }
+                }
                 if self.cleanup.as_mut().poll(cx).is_ready() {
-                    cx.waker().wake_by_ref();
                     self.state = ShutdownState::JoiningTasks;
+                    self.emit(ShutdownEvent::CleanupFinished);
+                    cx.waker().wake_by_ref();
                 }
                 Poll::Pending
             }
-            ShutdownState::JoiningTasks => match self.tasks.last_mut() {
-                Some(task) => {
-                    if task.as_mut().poll(cx).is_ready() {
-                        self.tasks.pop();
-                        cx.waker().wake_by_ref();
+            ShutdownState::JoiningTasks => {
+                if let Some(deadline) = &mut self.deadline {
+                    if deadline.as_mut().poll(cx).is_ready() {
+                        self.state = ShutdownState::Finished;
+                        self.emit(ShutdownEvent::Finished);
+                        return Poll::Ready(self.finish_timed_out());
                     }
+                }
+                let ready: VecDeque<ChildId> =
+                    std::mem::take(&mut self.shared.lock().unwrap().ready);
+                for id in ready {
+                    if let ChildId::Task(i) = id {
+                        if self.poll_task(i) {
+                            self.emit(ShutdownEvent::TaskJoined { index: i });
+                        }
+                    }
+                }
+                let all_done = self
+                    .tasks
+                    .iter()
+                    .all(|slot| matches!(slot, TaskSlot::Done(_)));
+                if all_done {
+                    self.state = ShutdownState::Finished;
+                    self.emit(ShutdownEvent::Finished);
+                    Poll::Ready(self.finish_completed())
+                } else {
                     Poll::Pending
                 }
-                None => Poll::Ready(()),
-            },
+            }
+            // Once finished, stay inert: `FusedFuture::is_terminated` tells
+            // callers not to poll again, but polling anyway must not panic.
+            ShutdownState::Finished => Poll::Pending,
+        }
+    }
+}
+
+impl<TriggerReturn, TaskReturn, F: Future<Output = ()>> FusedFuture
+    for ShutdownFuture<TriggerReturn, TaskReturn, F>
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, ShutdownState::Finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Immediate;
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        sync::atomic::{AtomicBool, Ordering},
+        task::{RawWaker, RawWakerVTable},
+    };
+
+    /// A future that sets `flag` and resolves on its first poll.
+    struct FlagOnPoll(Arc<AtomicBool>);
+
+    impl Future for FlagOnPoll {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            self.0.store(true, Ordering::SeqCst);
+            Poll::Ready(())
+        }
+    }
+
+    #[test]
+    fn cleanup_runs_after_trigger_fires() {
+        let cleanup_ran = Arc::new(AtomicBool::new(false));
+
+        let fut = ShutdownFuture::new(
+            vec![Box::pin(Immediate(Some(())))],
+            vec![Box::pin(Immediate(Some(())))],
+            FlagOnPoll(cleanup_ran.clone()),
+            None,
+        );
+
+        pollster::block_on(fut);
+        assert!(cleanup_ran.load(Ordering::SeqCst));
+    }
+
+    /// A future that needs `remaining` more `Pending` polls before resolving
+    /// to `value`, re-waking itself each time so an executor keeps driving
+    /// it to completion.
+    struct CountdownThenReady<T> {
+        remaining: u32,
+        value: Option<T>,
+    }
+
+    impl<T> Future for CountdownThenReady<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            // SAFETY: `CountdownThenReady` holds no pinned data.
+            let this = unsafe { self.get_unchecked_mut() };
+            if this.remaining == 0 {
+                Poll::Ready(this.value.take().expect("polled after completion"))
+            } else {
+                this.remaining -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn task_outputs_come_back_in_order() {
+        let fut = ShutdownFuture::new(
+            Vec::<Pin<Box<dyn Future<Output = ()>>>>::new(),
+            vec![
+                Box::pin(CountdownThenReady {
+                    remaining: 2,
+                    value: Some("a"),
+                }),
+                Box::pin(CountdownThenReady {
+                    remaining: 0,
+                    value: Some("b"),
+                }),
+                Box::pin(CountdownThenReady {
+                    remaining: 1,
+                    value: Some("c"),
+                }),
+            ],
+            Immediate(Some(())),
+            None,
+        );
+
+        let output = pollster::block_on(fut);
+        assert!(matches!(output.cause, ShutdownCause::Task { index: 1 }));
+        assert_eq!(output.tasks, vec![Some("a"), Some("b"), Some("c")]);
+    }
+
+    /// A future that never completes.
+    struct PendingForever<T>(std::marker::PhantomData<T>);
+
+    impl<T> Future for PendingForever<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn deadline_aborts_in_progress_join() {
+        let fut = ShutdownFuture::new(
+            vec![Box::pin(Immediate(Some(())))],
+            vec![Box::pin(PendingForever::<()>(std::marker::PhantomData))],
+            Immediate(Some(())),
+            Some(Box::pin(CountdownThenReady {
+                remaining: 1,
+                value: Some(()),
+            })),
+        );
+
+        let output = pollster::block_on(fut);
+        assert!(output.timed_out);
+        assert_eq!(output.tasks, vec![None]);
+    }
+
+    #[test]
+    fn on_transition_reports_every_step() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events_for_callback = events.clone();
+
+        let fut = ShutdownFuture::new(
+            vec![Box::pin(Immediate(Some(())))],
+            vec![Box::pin(Immediate(Some(())))],
+            Immediate(Some(())),
+            None,
+        )
+        .on_transition(move |event| {
+            events_for_callback.borrow_mut().push(match event {
+                ShutdownEvent::TriggerFired { index } => format!("TriggerFired({index})"),
+                ShutdownEvent::TaskFiredFirst { index } => format!("TaskFiredFirst({index})"),
+                ShutdownEvent::CleanupFinished => "CleanupFinished".to_string(),
+                ShutdownEvent::TaskJoined { index } => format!("TaskJoined({index})"),
+                ShutdownEvent::Finished => "Finished".to_string(),
+            });
+        });
+
+        pollster::block_on(fut);
+
+        assert_eq!(
+            *events.borrow(),
+            vec!["TriggerFired(0)", "CleanupFinished", "TaskJoined(0)", "Finished"],
+        );
+    }
+
+    /// A waker that does nothing; used to poll manually without an executor.
+    fn noop_waker() -> Waker {
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+    }
+
+    #[test]
+    fn is_terminated_flips_after_completion() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = ShutdownFuture::new(
+            vec![Box::pin(Immediate(Some(())))],
+            vec![Box::pin(Immediate(Some(())))],
+            Immediate(Some(())),
+            None,
+        );
+
+        assert!(!fut.is_terminated());
+        let mut pinned = Pin::new(&mut fut);
+        while pinned.as_mut().poll(&mut cx).is_pending() {
+            assert!(!pinned.is_terminated());
         }
+        assert!(pinned.is_terminated());
     }
 }
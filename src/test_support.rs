@@ -0,0 +1,21 @@
+//! Shared helper futures for `#[cfg(test)]` modules across the crate.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A future that resolves to the value it wraps on its first poll.
+pub(crate) struct Immediate<T>(pub(crate) Option<T>);
+
+impl<T> Future for Immediate<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+        // SAFETY: `Immediate` holds no pinned data, so it's fine to get
+        // a plain `&mut` to it.
+        let this = unsafe { self.get_unchecked_mut() };
+        Poll::Ready(this.0.take().expect("Immediate polled after completion"))
+    }
+}